@@ -0,0 +1,44 @@
+//! Bounds-checked binary accessors: every accessor validates that the
+//! requested span lies within the buffer before touching it, instead of
+//! trusting the caller.
+
+/// Read fixed-width big-endian fields out of a byte buffer without panicking
+/// on truncated input.
+pub trait BinUtil {
+    /// Read a 4-byte identifier (FourCC) at `i`.
+    fn c_iden(&self, i: usize) -> Result<[u8; 4], String>;
+    /// Read a big-endian `u32` at `i`.
+    fn c_u32b(&self, i: usize) -> Result<u32, String>;
+}
+
+impl BinUtil for [u8] {
+    fn c_iden(&self, i: usize) -> Result<[u8; 4], String> {
+        self.get(i..i + 4)
+            .map(|s| s.try_into().unwrap())
+            .ok_or_else(|| "not enough data".to_string())
+    }
+
+    fn c_u32b(&self, i: usize) -> Result<u32, String> {
+        self.c_iden(i).map(u32::from_be_bytes)
+    }
+}
+
+/// Parse a packed array of entries out of `buf`, starting at `start`,
+/// calling `read_fn` once per entry. `read_fn` returns the parsed item along
+/// with the number of bytes it consumed, and the cursor advances by that
+/// amount, which lets callers mix fixed- and variable-width entries.
+pub fn rd_ofstable<T, E>(
+    buf: &[u8],
+    start: usize,
+    count: usize,
+    read_fn: impl Fn(&[u8], usize) -> Result<(T, usize), E>,
+) -> Result<Vec<T>, E> {
+    let mut items = Vec::with_capacity(count);
+    let mut offset = start;
+    for _ in 0..count {
+        let (item, consumed) = read_fn(buf, offset)?;
+        items.push(item);
+        offset += consumed;
+    }
+    Ok(items)
+}
@@ -0,0 +1,66 @@
+//! Structured decode-failure diagnostics for Blorb/IFF parsing: one variant
+//! per distinct failure mode instead of a single catch-all string.
+
+use std::fmt;
+
+/// Everything that can go wrong while parsing a Blorb file.
+///
+/// Marked `#[non_exhaustive]` so new failure modes can be added later
+/// without it being a breaking change for downstream matches.
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// The stream ended before a required field could be read.
+    UnexpectedEof,
+    /// The file did not start with a `FORM` IFF header.
+    NotIffForm,
+    /// The FORM's sub-type was not `IFRS` (Blorb).
+    NotBlorb,
+    /// The Blorb file had no `RIdx` resource index chunk.
+    MissingResourceIndex,
+    /// A resource usage or chunk type code wasn't one this crate understands.
+    UnrecognizedChunk([u8; 4]),
+    /// A chunk declared a length that didn't match what was expected.
+    UnexpectedChunkLength { expected: usize, got: usize },
+    /// A PNG blob's signature, `IHDR` chunk, or chunk CRCs were invalid.
+    BadIhdr,
+    /// A lower-level I/O error occurred.
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => write!(f, "unexpected end of data"),
+            Error::NotIffForm => write!(f, "file is not an IFF document"),
+            Error::NotBlorb => write!(f, "IFF document is not a Blorb file"),
+            Error::MissingResourceIndex => {
+                write!(f, "Blorb file has no RIdx resource index")
+            }
+            Error::UnrecognizedChunk(code) => write!(
+                f,
+                "unrecognized chunk code '{}'",
+                code.iter().map(|b| *b as char).collect::<String>()
+            ),
+            Error::UnexpectedChunkLength { expected, got } => {
+                write!(f, "expected chunk length {expected}, got {got}")
+            }
+            Error::BadIhdr => write!(f, "invalid or corrupt PNG header"),
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<crate::fourcc::ReprError> for Error {
+    fn from(e: crate::fourcc::ReprError) -> Self {
+        Error::UnrecognizedChunk(e.0)
+    }
+}
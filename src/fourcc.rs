@@ -0,0 +1,52 @@
+//! A macro for declaring enums over four-character codes: each variant
+//! maps to a `[u8; 4]` literal, and the macro generates a bounds-checked
+//! `from_repr` plus a `Display` that prints the code as a quoted FourCC.
+
+/// A four-character code wasn't one of the variants declared for its enum.
+#[derive(Debug)]
+pub struct ReprError(pub [u8; 4]);
+
+impl std::fmt::Display for ReprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unrecognized four-character code '{}'",
+            self.0.iter().map(|b| *b as char).collect::<String>()
+        )
+    }
+}
+
+impl std::error::Error for ReprError {}
+
+macro_rules! c_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident = $code:expr),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        $vis enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub fn from_repr(code: [u8; 4]) -> Result<Self, crate::fourcc::ReprError> {
+                $(if code == $code { return Ok(Self::$variant); })+
+                Err(crate::fourcc::ReprError(code))
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let code: [u8; 4] = match self {
+                    $(Self::$variant => $code,)+
+                };
+                write!(f, "'{}'", code.iter().map(|b| *b as char).collect::<String>())
+            }
+        }
+    };
+}
+
+pub(crate) use c_enum;
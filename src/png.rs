@@ -0,0 +1,177 @@
+//! A minimal native PNG front-end: just enough to validate a PNG blob and
+//! read its declared `width`/`height` without decoding any pixel data, so
+//! `read_pict` can size and sanity-check `Png` resources without pulling
+//! in a full decoder.
+
+use crate::binutil::BinUtil;
+use crate::error::Error;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Validate a PNG blob's signature, `IHDR` chunk, and chunk CRCs, returning
+/// its declared width and height without decoding any pixel data.
+pub fn png_dimensions(data: &[u8]) -> Result<(u32, u32), Error> {
+    if data.get(..SIGNATURE.len()) != Some(SIGNATURE.as_slice()) {
+        return Err(Error::BadIhdr);
+    }
+
+    let mut stream = chunks(data);
+    let (kind, payload) = stream.next().ok_or(Error::BadIhdr)??;
+    if kind != *b"IHDR" {
+        return Err(Error::BadIhdr);
+    }
+    let dimensions = parse_ihdr(payload)?;
+
+    // Walking (and CRC-checking) the rest of the stream catches a PNG that
+    // declares valid dimensions but whose IDAT data has been scrambled.
+    for chunk in stream {
+        chunk?;
+    }
+
+    Ok(dimensions)
+}
+
+fn parse_ihdr(payload: &[u8]) -> Result<(u32, u32), Error> {
+    if payload.len() != 13 {
+        return Err(Error::BadIhdr);
+    }
+    let width = payload.c_u32b(0).map_err(|_| Error::BadIhdr)?;
+    let height = payload.c_u32b(4).map_err(|_| Error::BadIhdr)?;
+    let bit_depth = payload[8];
+    let color_type = payload[9];
+    let compression = payload[10];
+    let filter = payload[11];
+    let interlace = payload[12];
+
+    if width == 0 || height == 0 {
+        return Err(Error::BadIhdr);
+    }
+    if compression != 0 || filter != 0 || interlace > 1 {
+        return Err(Error::BadIhdr);
+    }
+    if !allowed_bit_depths(color_type).contains(&bit_depth) {
+        return Err(Error::BadIhdr);
+    }
+
+    Ok((width, height))
+}
+
+/// The bit depths the PNG spec permits for each color type.
+fn allowed_bit_depths(color_type: u8) -> &'static [u8] {
+    match color_type {
+        0 => &[1, 2, 4, 8, 16], // grayscale
+        2 => &[8, 16],          // truecolor
+        3 => &[1, 2, 4, 8],     // indexed
+        4 => &[8, 16],          // grayscale + alpha
+        6 => &[8, 16],          // truecolor + alpha
+        _ => &[],
+    }
+}
+
+/// Walk the chunk stream following the signature, yielding each chunk's
+/// type and payload after verifying its trailing CRC32.
+fn chunks(data: &[u8]) -> impl Iterator<Item = Result<([u8; 4], &[u8]), Error>> + '_ {
+    let mut pos = SIGNATURE.len();
+    std::iter::from_fn(move || {
+        if pos >= data.len() {
+            return None;
+        }
+        Some((|| {
+            let len = data.c_u32b(pos).map_err(|_| Error::UnexpectedEof)? as usize;
+            let kind = data.c_iden(pos + 4).map_err(|_| Error::UnexpectedEof)?;
+            let payload = data
+                .get(pos + 8..pos + 8 + len)
+                .ok_or(Error::UnexpectedEof)?;
+            let crc = data
+                .c_u32b(pos + 8 + len)
+                .map_err(|_| Error::UnexpectedEof)?;
+            if crc32(&kind, payload) != crc {
+                return Err(Error::BadIhdr);
+            }
+            pos += 12 + len;
+            Ok((kind, payload))
+        })())
+    })
+}
+
+/// The CRC-32 variant PNG uses (zlib/IEEE 802.3 polynomial), computed over
+/// a chunk's type and data.
+fn crc32(kind: &[u8; 4], payload: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in kind.iter().chain(payload.iter()) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc ^ 0xFFFF_FFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(payload);
+        out.extend_from_slice(&crc32(kind, payload).to_be_bytes());
+        out
+    }
+
+    fn ihdr_payload(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&width.to_be_bytes());
+        out.extend_from_slice(&height.to_be_bytes());
+        out.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+        out
+    }
+
+    fn minimal_png(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+        let mut out = SIGNATURE.to_vec();
+        out.extend(chunk(
+            b"IHDR",
+            &ihdr_payload(width, height, bit_depth, color_type),
+        ));
+        out.extend(chunk(b"IEND", &[]));
+        out
+    }
+
+    #[test]
+    fn valid_png_reports_dimensions() {
+        let data = minimal_png(4, 3, 8, 2);
+        assert_eq!(png_dimensions(&data).unwrap(), (4, 3));
+    }
+
+    #[test]
+    fn truncated_signature_is_rejected() {
+        let data = &SIGNATURE[..4];
+        assert!(matches!(png_dimensions(data), Err(Error::BadIhdr)));
+    }
+
+    #[test]
+    fn bad_crc_is_rejected() {
+        let mut data = minimal_png(4, 3, 8, 2);
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        assert!(matches!(png_dimensions(&data), Err(Error::BadIhdr)));
+    }
+
+    #[test]
+    fn zero_dimension_is_rejected() {
+        let data = minimal_png(0, 3, 8, 2);
+        assert!(matches!(png_dimensions(&data), Err(Error::BadIhdr)));
+    }
+
+    #[test]
+    fn illegal_bit_depth_for_color_type_is_rejected() {
+        // Color type 2 (truecolor) only permits bit depths 8 and 16.
+        let data = minimal_png(4, 3, 4, 2);
+        assert!(matches!(png_dimensions(&data), Err(Error::BadIhdr)));
+    }
+}
@@ -0,0 +1,521 @@
+//! Parses a Blorb resource table and lazily loads individual chunks.
+//!
+//! [`BlorbHeader`] owns the parsed FORM/IFRS/RIdx metadata and resource
+//! table, while [`BlorbReader`] wraps any `Read + Seek` source (a `File`, a
+//! `Cursor<Vec<u8>>`, ...) and knows how to fetch a resource's bytes on
+//! demand.
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::binutil::{rd_ofstable, BinUtil};
+use crate::error::Error;
+use crate::fourcc::c_enum;
+
+const FORM: [u8; 4] = [b'F', b'O', b'R', b'M'];
+const IFRS: [u8; 4] = [b'I', b'F', b'R', b'S'];
+const RIDX: [u8; 4] = [b'R', b'I', b'd', b'x'];
+
+c_enum! {
+    /// A resource's usage, as declared by its RIdx table entry.
+    pub enum ChunkType {
+        Pict = [b'P', b'i', b'c', b't'],
+        Exec = [b'E', b'x', b'e', b'c'],
+        Sound = [b'S', b'n', b'd', b' '],
+        Data = [b'D', b'a', b't', b'a'],
+    }
+}
+
+c_enum! {
+    /// The format of a `Pict` resource's chunk.
+    enum PictFormat {
+        Jpeg = [b'J', b'P', b'E', b'G'],
+        Png = [b'P', b'N', b'G', b' '],
+        Rect = [b'R', b'e', b'c', b't'],
+    }
+}
+
+c_enum! {
+    /// The format of a `Snd ` resource's chunk.
+    enum SoundFormat {
+        Oggv = [b'O', b'G', b'G', b'V'],
+        Aiff = [b'A', b'I', b'F', b'F'],
+        Mod = [b'M', b'O', b'D', b' '],
+    }
+}
+
+c_enum! {
+    /// The format of a `Data` resource's chunk.
+    enum DataFormat {
+        Text = [b'T', b'E', b'X', b'T'],
+        Bina = [b'B', b'I', b'N', b'A'],
+    }
+}
+
+#[derive(Debug)]
+pub enum PictResource {
+    Png { data: Vec<u8>, width: u32, height: u32 },
+    Jpeg { data: Vec<u8> },
+    Rect { width: usize, height: usize },
+}
+
+#[derive(Debug)]
+pub enum SoundResource {
+    Aiff { data: Vec<u8> },
+    Ogg { data: Vec<u8> },
+    Mod { data: Vec<u8> },
+}
+
+#[derive(Debug)]
+pub enum DataResource {
+    Text { data: Vec<u8> },
+    Bina { data: Vec<i32> },
+}
+
+#[derive(Debug)]
+pub enum ChunkResource {
+    Pict(PictResource),
+    Exec(Vec<u8>),
+    Sound(SoundResource),
+    Data(DataResource),
+}
+
+/// A resource table entry: what it is, its resource number, and where its
+/// chunk starts in the underlying stream. Resource bytes aren't loaded
+/// until [`BlorbReader::load_chunk`] is called with this entry.
+#[derive(Debug, Clone)]
+pub struct ChunkInfo {
+    pub usage: ChunkType,
+    pub number: usize,
+    start: u64,
+}
+
+impl Display for ChunkInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}: {} @{}]", self.number, self.usage, self.start)
+    }
+}
+
+/// Parsed FORM/IFRS/RIdx metadata and the resource table, without any
+/// resource bytes loaded.
+#[derive(Debug)]
+pub struct BlorbHeader {
+    pub form_size: usize,
+    pub ridx_size: usize,
+    pub resources: Vec<ChunkInfo>,
+}
+
+/// Reads a Blorb file's table of contents up front, then lazily loads
+/// individual resource chunks from any seekable source.
+pub struct BlorbReader<R: Read + Seek> {
+    inner: R,
+    header: BlorbHeader,
+    strict: bool,
+}
+
+impl<R: Read + Seek> BlorbReader<R> {
+    /// Open `inner` and parse its table of contents. A sub-chunk whose
+    /// declared length overruns the enclosing FORM is a hard error.
+    pub fn new(inner: R) -> Result<Self, Error> {
+        Self::with_strictness(inner, true)
+    }
+
+    /// Like [`new`](Self::new), but a sub-chunk length that overruns the
+    /// enclosing FORM is clamped to what's available (with a warning)
+    /// instead of erroring. Needed for real Blorb files produced by tools
+    /// that emit odd-length AIFF/TEXT payloads with an imprecise FORM size.
+    pub fn new_lenient(inner: R) -> Result<Self, Error> {
+        Self::with_strictness(inner, false)
+    }
+
+    fn with_strictness(mut inner: R, strict: bool) -> Result<Self, Error> {
+        let header = read_header(&mut inner)?;
+        Ok(BlorbReader {
+            inner,
+            header,
+            strict,
+        })
+    }
+
+    pub fn header(&self) -> &BlorbHeader {
+        &self.header
+    }
+
+    /// All resources in table-of-contents order.
+    pub fn resources(&self) -> &[ChunkInfo] {
+        &self.header.resources
+    }
+
+    /// Look up a single resource by its usage and resource number, without
+    /// loading its chunk bytes.
+    pub fn resource(&self, usage: ChunkType, number: usize) -> Option<&ChunkInfo> {
+        self.header
+            .resources
+            .iter()
+            .find(|chunk| chunk.usage == usage && chunk.number == number)
+    }
+
+    /// Seek to `chunk` and read its resource bytes.
+    pub fn load_chunk(&mut self, chunk: &ChunkInfo) -> Result<ChunkResource, Error> {
+        let form_end = 8 + self.header.form_size as u64;
+        match chunk.usage {
+            ChunkType::Pict => Ok(ChunkResource::Pict(read_pict(
+                &mut self.inner,
+                chunk.start,
+                form_end,
+                self.strict,
+            )?)),
+            ChunkType::Exec => {
+                self.inner.seek(SeekFrom::Start(chunk.start))?;
+                read_type(&mut self.inner)?;
+                let chunk_len = read_size(&mut self.inner)?;
+                let read_len =
+                    bounded_chunk_len(chunk.start, chunk_len, form_end, self.strict)?;
+                Ok(ChunkResource::Exec(read_bytes(&mut self.inner, read_len)?))
+            }
+            ChunkType::Sound => Ok(ChunkResource::Sound(read_sound(
+                &mut self.inner,
+                chunk.start,
+                form_end,
+                self.strict,
+            )?)),
+            ChunkType::Data => Ok(ChunkResource::Data(read_data(
+                &mut self.inner,
+                chunk.start,
+                form_end,
+                self.strict,
+            )?)),
+        }
+    }
+}
+
+/// Validate `chunk_len` against the bytes actually available before the
+/// enclosing FORM ends, returning the length to read. In strict mode an
+/// overrun is an error; in lenient mode it's clamped with a warning.
+fn bounded_chunk_len(
+    chunk_start: u64,
+    chunk_len: usize,
+    form_end: u64,
+    strict: bool,
+) -> Result<usize, Error> {
+    let declared_end = chunk_start + 8 + chunk_len as u64;
+    if declared_end <= form_end {
+        return Ok(chunk_len);
+    }
+    let available = form_end.saturating_sub(chunk_start + 8) as usize;
+    if strict {
+        return Err(Error::UnexpectedChunkLength {
+            expected: available,
+            got: chunk_len,
+        });
+    }
+    eprintln!(
+        "warning: chunk at offset {chunk_start} declares length {chunk_len}, \
+         which overruns the enclosing FORM; clamping to {available}"
+    );
+    Ok(available)
+}
+
+fn read_header<R: Read + Seek>(f: &mut R) -> Result<BlorbHeader, Error> {
+    let file_type = read_type(f)?;
+    let form_size = read_size(f)?;
+    if file_type != FORM {
+        return Err(Error::NotIffForm);
+    }
+
+    let form_type = read_type(f)?;
+    if form_type != IFRS {
+        return Err(Error::NotBlorb);
+    }
+
+    let ridx_type = read_type(f)?;
+    if ridx_type != RIDX {
+        return Err(Error::MissingResourceIndex);
+    }
+
+    let ridx_size = read_size(f)?;
+    let resource_count = read_size(f)?;
+
+    const RESOURCE_INFO_LEN: usize = 12;
+    let table = read_bytes(f, resource_count * RESOURCE_INFO_LEN)?;
+    let resources = rd_ofstable(&table, 0, resource_count, |buf, i| {
+        read_resource_info(buf, i).map(|chunk| (chunk, RESOURCE_INFO_LEN))
+    })?;
+
+    Ok(BlorbHeader {
+        form_size,
+        ridx_size,
+        resources,
+    })
+}
+
+/// Read exactly `n` bytes from `f`, erroring (instead of silently returning
+/// a short or zero-filled buffer) if the stream runs out first. Uses
+/// `read_exact` rather than a single `read` call, since `Read::read` is
+/// allowed to return fewer bytes than requested without being at EOF (e.g.
+/// pipes/sockets, or an interrupted syscall).
+fn read_bytes<R: Read>(f: &mut R, n: usize) -> Result<Vec<u8>, Error> {
+    let mut buffer = vec![0u8; n];
+    f.read_exact(&mut buffer).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+        _ => Error::from(e),
+    })?;
+    Ok(buffer)
+}
+
+fn read_type<R: Read>(f: &mut R) -> Result<[u8; 4], Error> {
+    let buffer = read_bytes(f, 4)?;
+    buffer.as_slice().c_iden(0).map_err(|_| Error::UnexpectedEof)
+}
+
+fn read_size<R: Read>(f: &mut R) -> Result<usize, Error> {
+    let buffer = read_bytes(f, 4)?;
+    buffer
+        .as_slice()
+        .c_u32b(0)
+        .map(|v| v as usize)
+        .map_err(|_| Error::UnexpectedEof)
+}
+
+fn read_resource_info(buf: &[u8], i: usize) -> Result<ChunkInfo, Error> {
+    let usage_id = buf.c_iden(i).map_err(|_| Error::UnexpectedEof)?;
+    let usage = ChunkType::from_repr(usage_id)?;
+    let number = buf.c_u32b(i + 4).map_err(|_| Error::UnexpectedEof)? as usize;
+    let start = buf.c_u32b(i + 8).map_err(|_| Error::UnexpectedEof)? as u64;
+    Ok(ChunkInfo {
+        usage,
+        number,
+        start,
+    })
+}
+
+fn read_pict<R: Read + Seek>(
+    f: &mut R,
+    offset: u64,
+    form_end: u64,
+    strict: bool,
+) -> Result<PictResource, Error> {
+    f.seek(SeekFrom::Start(offset))?;
+    let chunk_type = read_type(f)?;
+    let chunk_len = read_size(f)?;
+    let read_len = bounded_chunk_len(offset, chunk_len, form_end, strict)?;
+    let data = read_bytes(f, read_len)?;
+
+    match PictFormat::from_repr(chunk_type)? {
+        PictFormat::Jpeg => Ok(PictResource::Jpeg { data }),
+        PictFormat::Png => {
+            let (width, height) = crate::png::png_dimensions(&data)?;
+            Ok(PictResource::Png { data, width, height })
+        }
+        PictFormat::Rect => {
+            // A Rect resource's payload IS its width/height pair (already
+            // read into `data` above) - there's nothing further to read.
+            // Check against `data.len()`, not `chunk_len`: in lenient mode
+            // `bounded_chunk_len` may have clamped the read to fewer bytes
+            // than the chunk declared.
+            if data.len() != 8 {
+                return Err(Error::UnexpectedChunkLength {
+                    expected: 8,
+                    got: data.len(),
+                });
+            }
+            let width = data.as_slice().c_u32b(0).map_err(|_| Error::UnexpectedEof)? as usize;
+            let height = data.as_slice().c_u32b(4).map_err(|_| Error::UnexpectedEof)? as usize;
+            Ok(PictResource::Rect { width, height })
+        }
+    }
+}
+
+fn read_sound<R: Read + Seek>(
+    f: &mut R,
+    offset: u64,
+    form_end: u64,
+    strict: bool,
+) -> Result<SoundResource, Error> {
+    f.seek(SeekFrom::Start(offset))?;
+    let chunk_type = read_type(f)?;
+    let chunk_len = read_size(f)?;
+    let read_len = bounded_chunk_len(offset, chunk_len, form_end, strict)?;
+    let data = read_bytes(f, read_len)?;
+
+    match SoundFormat::from_repr(chunk_type)? {
+        SoundFormat::Oggv => Ok(SoundResource::Ogg { data }),
+        SoundFormat::Mod => Ok(SoundResource::Mod { data }),
+        SoundFormat::Aiff => Ok(SoundResource::Aiff { data }),
+    }
+}
+
+fn read_data<R: Read + Seek>(
+    f: &mut R,
+    offset: u64,
+    form_end: u64,
+    strict: bool,
+) -> Result<DataResource, Error> {
+    f.seek(SeekFrom::Start(offset))?;
+    let chunk_type = read_type(f)?;
+    let chunk_len = read_size(f)?;
+    let read_len = bounded_chunk_len(offset, chunk_len, form_end, strict)?;
+    let data = read_bytes(f, read_len)?;
+
+    match DataFormat::from_repr(chunk_type)? {
+        DataFormat::Text => Ok(DataResource::Text { data }),
+        DataFormat::Bina => {
+            let count = data.len() / 4;
+            let mut bin_data = Vec::with_capacity(count);
+            for i in 0..count {
+                let xdata = data.as_slice().c_u32b(i * 4).map_err(|_| Error::UnexpectedEof)?;
+                let lower_31 = xdata & 0x7FFF_FFFF;
+                let sign_bit = xdata & 0x8000_0000;
+                let value: i32 = if sign_bit != 0 {
+                    -(lower_31 as i32)
+                } else {
+                    lower_31 as i32
+                };
+                bin_data.push(value)
+            }
+            Ok(DataResource::Bina { data: bin_data })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn bounded_chunk_len_passes_through_when_in_bounds() {
+        let len = bounded_chunk_len(0, 8, 16, true).unwrap();
+        assert_eq!(len, 8);
+    }
+
+    #[test]
+    fn bounded_chunk_len_errors_on_overrun_in_strict_mode() {
+        let err = bounded_chunk_len(0, 100, 16, true).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnexpectedChunkLength { expected: 8, got: 100 }
+        ));
+    }
+
+    #[test]
+    fn bounded_chunk_len_clamps_on_overrun_in_lenient_mode() {
+        let len = bounded_chunk_len(0, 100, 16, false).unwrap();
+        assert_eq!(len, 8);
+    }
+
+    /// Builds a minimal single-resource Blorb file containing one `Rect`
+    /// Pict resource, to exercise the table-of-contents and chunk parsing
+    /// together the way a real file would.
+    fn minimal_rect_blorb(width: u32, height: u32) -> Vec<u8> {
+        const RESOURCE_INFO_LEN: u32 = 12; // usage (4) + number (4) + start (4)
+        let chunk_start = 8 + 4 + 4 + 4 + 4 + RESOURCE_INFO_LEN; // FORM+size, IFRS, RIdx, ridx_size, count, table entry
+        let mut table_entry = Vec::new();
+        table_entry.extend_from_slice(b"Pict");
+        table_entry.extend_from_slice(&0u32.to_be_bytes()); // resource number
+        table_entry.extend_from_slice(&chunk_start.to_be_bytes());
+
+        let mut rect_chunk = Vec::new();
+        rect_chunk.extend_from_slice(b"Rect");
+        rect_chunk.extend_from_slice(&8u32.to_be_bytes());
+        rect_chunk.extend_from_slice(&width.to_be_bytes());
+        rect_chunk.extend_from_slice(&height.to_be_bytes());
+
+        let mut ridx = Vec::new();
+        ridx.extend_from_slice(b"RIdx");
+        let ridx_size = 4 + table_entry.len() as u32;
+        ridx.extend_from_slice(&ridx_size.to_be_bytes());
+        ridx.extend_from_slice(&1u32.to_be_bytes()); // resource count
+        ridx.extend_from_slice(&table_entry);
+
+        let mut form = Vec::new();
+        form.extend_from_slice(b"FORM");
+        let form_size = (4 + ridx.len() + rect_chunk.len()) as u32; // "IFRS" + RIdx + Pict chunk
+        form.extend_from_slice(&form_size.to_be_bytes());
+        form.extend_from_slice(b"IFRS");
+        form.extend_from_slice(&ridx);
+        form.extend_from_slice(&rect_chunk);
+        form
+    }
+
+    #[test]
+    fn resource_looks_up_by_usage_and_number() {
+        let bytes = minimal_rect_blorb(7, 5);
+        let blorb = BlorbReader::new(Cursor::new(bytes)).unwrap();
+        let found = blorb.resource(ChunkType::Pict, 0).unwrap();
+        assert_eq!(found.number, 0);
+        assert!(blorb.resource(ChunkType::Pict, 1).is_none());
+        assert!(blorb.resource(ChunkType::Sound, 0).is_none());
+    }
+
+    #[test]
+    fn round_trips_a_single_rect_resource() {
+        let bytes = minimal_rect_blorb(7, 5);
+        let mut blorb = BlorbReader::new(Cursor::new(bytes)).unwrap();
+        let chunk = blorb.resources()[0].clone();
+        match blorb.load_chunk(&chunk).unwrap() {
+            ChunkResource::Pict(PictResource::Rect { width, height }) => {
+                assert_eq!((width, height), (7, 5));
+            }
+            other => panic!("expected a Rect resource, got {other:?}"),
+        }
+    }
+
+    /// Like [`minimal_rect_blorb`], but the `Rect` chunk declares a length
+    /// overrunning the FORM even though only 8 bytes of payload are
+    /// actually present - the case lenient mode is meant to salvage.
+    fn rect_blorb_with_overrunning_declared_len(width: u32, height: u32) -> Vec<u8> {
+        const RESOURCE_INFO_LEN: u32 = 12;
+        let chunk_start = 8 + 4 + 4 + 4 + 4 + RESOURCE_INFO_LEN;
+        let mut table_entry = Vec::new();
+        table_entry.extend_from_slice(b"Pict");
+        table_entry.extend_from_slice(&0u32.to_be_bytes());
+        table_entry.extend_from_slice(&chunk_start.to_be_bytes());
+
+        let mut rect_chunk = Vec::new();
+        rect_chunk.extend_from_slice(b"Rect");
+        rect_chunk.extend_from_slice(&100u32.to_be_bytes()); // declared length overruns the FORM
+        rect_chunk.extend_from_slice(&width.to_be_bytes());
+        rect_chunk.extend_from_slice(&height.to_be_bytes()); // only 8 bytes of payload actually present
+
+        let mut ridx = Vec::new();
+        ridx.extend_from_slice(b"RIdx");
+        let ridx_size = 4 + table_entry.len() as u32;
+        ridx.extend_from_slice(&ridx_size.to_be_bytes());
+        ridx.extend_from_slice(&1u32.to_be_bytes());
+        ridx.extend_from_slice(&table_entry);
+
+        let mut form = Vec::new();
+        form.extend_from_slice(b"FORM");
+        let form_size = (4 + ridx.len() + rect_chunk.len()) as u32;
+        form.extend_from_slice(&form_size.to_be_bytes());
+        form.extend_from_slice(b"IFRS");
+        form.extend_from_slice(&ridx);
+        form.extend_from_slice(&rect_chunk);
+        form
+    }
+
+    #[test]
+    fn strict_mode_rejects_a_rect_with_overrunning_declared_len() {
+        let bytes = rect_blorb_with_overrunning_declared_len(7, 5);
+        let mut blorb = BlorbReader::new(Cursor::new(bytes)).unwrap();
+        let chunk = blorb.resources()[0].clone();
+        assert!(matches!(
+            blorb.load_chunk(&chunk),
+            Err(Error::UnexpectedChunkLength { expected: 8, .. })
+        ));
+    }
+
+    #[test]
+    fn lenient_mode_salvages_a_rect_with_overrunning_declared_len() {
+        let bytes = rect_blorb_with_overrunning_declared_len(7, 5);
+        let mut blorb = BlorbReader::new_lenient(Cursor::new(bytes)).unwrap();
+        let chunk = blorb.resources()[0].clone();
+        match blorb.load_chunk(&chunk).unwrap() {
+            ChunkResource::Pict(PictResource::Rect { width, height }) => {
+                assert_eq!((width, height), (7, 5));
+            }
+            other => panic!("expected a Rect resource, got {other:?}"),
+        }
+    }
+}